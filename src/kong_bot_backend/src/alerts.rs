@@ -18,8 +18,12 @@ use ic_cdk::{
 };
 use serde_json::Value;
 use candid::{CandidType, Deserialize};
-use std::collections::HashMap;
-use crate::trading::Portfolio;
+use rust_decimal::Decimal;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
 
 
 
@@ -37,21 +41,149 @@ pub struct Alert {
     coin: String,
     /// Target price in USD that triggers the alert
     target_price: f64,
+    /// What to do when `target_price` is crossed
+    action: AlertAction,
+    /// Set once `action` has executed for the current crossing, to prevent double-execution
+    /// until the alert is explicitly re-armed via `rearm_alert`
+    fired: bool,
+    /// Which side of `target_price` the most recent crossing approached from: `Some(true)` if
+    /// price rose through it, `Some(false)` if it fell through it, `None` before any crossing.
+    /// Used to keep retrying `action` every tick the price remains on that side while `!fired`,
+    /// rather than only on the single tick the crossing itself was observed.
+    triggered_rising: Option<bool>,
+    /// Outcome of the most recent OpenChat delivery attempt for this alert, if any
+    last_delivery: Option<DeliveryStatus>,
+}
+
+/// Outcome of a single OpenChat delivery attempt
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct DeliveryStatus {
+    /// When the delivery attempt was made
+    timestamp_ns: u64,
+    /// Whether OpenChat acknowledged the message with a 2xx response
+    success: bool,
+    /// Response detail, or the transport/error message on failure
+    detail: String,
+}
+
+/// What a triggered [`Alert`] does beyond sending an OpenChat notification
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq)]
+pub enum AlertAction {
+    /// Notify the user only; no trade is executed
+    Notify,
+    /// Buy `quote_amount_usd` worth of the alert's coin at the observed price
+    Buy { quote_amount_usd: f64 },
+    /// Sell the entire holding of the alert's coin at the observed price
+    SellAll,
+    /// Sell `pct` percent (0-100) of the holding of the alert's coin at the observed price
+    SellFraction { pct: f64 },
+}
+
+impl Default for AlertAction {
+    fn default() -> Self {
+        AlertAction::Notify
+    }
 }
 
 /// Type alias for the alerts storage map
 type Alerts = HashMap<String, Alert>;
 
-/// Tracks price history for cryptocurrencies
+/// A single price observation
+#[derive(Clone, Debug, CandidType, Deserialize)]
+struct PriceSample {
+    /// Nanosecond timestamp the sample was recorded at, from `ic_cdk::api::time()`
+    timestamp_ns: u64,
+    /// Price in USD at `timestamp_ns`
+    price: f64,
+}
+
+/// Maximum number of samples retained per coin before the oldest are dropped
+const MAX_SAMPLES_PER_COIN: usize = 10_000;
+
+/// An append-only, capacity-bounded series of price samples for one coin
+#[derive(Clone, Debug, CandidType, Deserialize, Default)]
+struct PriceSeries {
+    /// Samples ordered oldest-first; capped at `MAX_SAMPLES_PER_COIN`
+    samples: VecDeque<PriceSample>,
+}
+
+impl PriceSeries {
+    /// Record a new price sample, dropping the oldest sample if at capacity
+    ///
+    /// Uses a ring buffer (`VecDeque`) rather than a `Vec` so eviction at capacity is O(1)
+    /// instead of shifting up to `MAX_SAMPLES_PER_COIN` elements on every sample.
+    fn push(&mut self, price: f64) {
+        if self.samples.len() >= MAX_SAMPLES_PER_COIN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(PriceSample {
+            timestamp_ns: api::time(),
+            price,
+        });
+    }
+
+    /// The most recently recorded price, if any
+    fn last_price(&self) -> Option<f64> {
+        self.samples.back().map(|s| s.price)
+    }
+}
+
+/// Type alias for the price series storage map
+type PriceSeriesMap = HashMap<String, PriceSeries>;
+
+/// Last-recorded price for a cryptocurrency, returned by the legacy [`get_price_history`] query
 #[derive(Clone, Debug, CandidType, Deserialize)]
 struct PriceHistory {
     /// Last recorded price in USD
     last_price: f64,
 }
 
-/// Type alias for the price history storage map
+/// Type alias for the (legacy) price history response map
 type PriceMap = HashMap<String, PriceHistory>;
 
+/// An open/high/low/close candle over a fixed time bucket
+#[derive(Clone, Debug, CandidType, Deserialize)]
+struct Candle {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    start_ts: u64,
+}
+
+/// Cache and retry behavior for `get_crypto_price`
+#[derive(Clone, Debug, CandidType, Deserialize)]
+struct PriceCacheConfig {
+    /// How long a cached price is considered fresh, in nanoseconds
+    ttl_ns: u64,
+    /// Number of retries on a 429/5xx response before giving up
+    max_retries: u32,
+}
+
+impl Default for PriceCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_ns: 60 * 1_000_000_000,
+            max_retries: 3,
+        }
+    }
+}
+
+/// Bearer-token configuration for the OpenChat messaging outcall
+#[derive(Clone, Debug, CandidType, Deserialize, Default)]
+struct OpenChatConfig {
+    /// Sent as `Authorization: Bearer <token>`; empty until configured via `set_openchat_token`
+    bearer_token: String,
+}
+
+thread_local! {
+    /// coin_id -> (price, fetched_at_ns); short-lived and not persisted across upgrades
+    static PRICE_CACHE: RefCell<HashMap<String, (f64, u64)>> = RefCell::new(HashMap::new());
+}
+
+/// Stable storage layout: alerts, price series, oracle config, cache config, OpenChat config
+type StableState = (Alerts, PriceSeriesMap, OracleConfig, PriceCacheConfig, OpenChatConfig);
+
 // ===== Storage Management =====
 
 /// Initialize the canister with empty storage
@@ -59,77 +191,209 @@ type PriceMap = HashMap<String, PriceHistory>;
 fn init() {
     let _ = storage::stable_save((
         HashMap::<String, Alert>::new(),
-        HashMap::<String, PriceHistory>::new(),
+        PriceSeriesMap::new(),
+        OracleConfig::default(),
+        PriceCacheConfig::default(),
+        OpenChatConfig::default(),
     ))
     .map_err(|e| api::print(format!("❌ Failed to initialize storage: {}", e)));
 }
 
 /// Load alerts from stable storage
 fn load_alerts() -> Alerts {
-    storage::stable_restore::<(Alerts, PriceMap)>()
-        .map(|(alerts, _)| alerts)
+    storage::stable_restore::<StableState>()
+        .map(|(alerts, _, _, _, _)| alerts)
         .unwrap_or_else(|e| {
             api::print(format!("⚠️ Failed to load alerts: {}", e));
             HashMap::new()
         })
 }
 
-/// Load price history from stable storage
-fn load_price_history() -> PriceMap {
-    storage::stable_restore::<(Alerts, PriceMap)>()
-        .map(|(_, prices)| prices)
+/// Load the per-coin price series from stable storage
+fn load_price_series() -> PriceSeriesMap {
+    storage::stable_restore::<StableState>()
+        .map(|(_, series, _, _, _)| series)
         .unwrap_or_else(|e| {
-            api::print(format!("⚠️ Failed to load price history: {}", e));
+            api::print(format!("⚠️ Failed to load price series: {}", e));
             HashMap::new()
         })
 }
-/// Get the current price history for all tracked cryptocurrencies
-/// 
+
+/// Load the price oracle configuration from stable storage
+fn load_oracle_config() -> OracleConfig {
+    storage::stable_restore::<StableState>()
+        .map(|(_, _, config, _, _)| config)
+        .unwrap_or_else(|e| {
+            api::print(format!("⚠️ Failed to load oracle config: {}", e));
+            OracleConfig::default()
+        })
+}
+
+/// Load the price cache/retry configuration from stable storage
+fn load_cache_config() -> PriceCacheConfig {
+    storage::stable_restore::<StableState>()
+        .map(|(_, _, _, cache_config, _)| cache_config)
+        .unwrap_or_else(|e| {
+            api::print(format!("⚠️ Failed to load price cache config: {}", e));
+            PriceCacheConfig::default()
+        })
+}
+
+/// Load the OpenChat messaging configuration from stable storage
+fn load_openchat_config() -> OpenChatConfig {
+    storage::stable_restore::<StableState>()
+        .map(|(_, _, _, _, openchat_config)| openchat_config)
+        .unwrap_or_else(|e| {
+            api::print(format!("⚠️ Failed to load OpenChat config: {}", e));
+            OpenChatConfig::default()
+        })
+}
+
+/// Get the last recorded price for all tracked cryptocurrencies
+///
 /// # Returns
 /// A map of cryptocurrency IDs to their last recorded prices
 #[query]
 fn get_price_history() -> PriceMap {
-    load_price_history()
+    load_price_series()
+        .into_iter()
+        .filter_map(|(coin, series)| series.last_price().map(|last_price| (coin, PriceHistory { last_price })))
+        .collect()
 }
 
-/// Save both alerts and price history to stable storage
-fn save_state(alerts: &Alerts, prices: &PriceMap) -> Result<(), String> {
-    storage::stable_save((alerts.clone(), prices.clone()))
-        .map_err(|e| format!("Failed to save state: {}", e))
+/// Bucket a coin's recorded price samples into fixed-width OHLC candles
+///
+/// # Parameters
+/// * `coin` - Cryptocurrency identifier (e.g., "btc", "bitcoin")
+/// * `interval_secs` - Width of each candle in seconds
+/// * `count` - Maximum number of most-recent candles to return
+///
+/// # Returns
+/// Candles ordered oldest-first; buckets with no samples are skipped
+#[query]
+fn get_price_candles(coin: String, interval_secs: u64, count: u64) -> Vec<Candle> {
+    if interval_secs == 0 || count == 0 {
+        return Vec::new();
+    }
+
+    let coin_id = to_coingecko_id(&coin).to_string();
+    let series = load_price_series();
+    let samples = match series.get(&coin_id) {
+        Some(series) => &series.samples,
+        None => return Vec::new(),
+    };
+
+    let interval_ns = interval_secs * 1_000_000_000;
+    let mut buckets: std::collections::BTreeMap<u64, Vec<&PriceSample>> = std::collections::BTreeMap::new();
+    for sample in samples {
+        let bucket_start = (sample.timestamp_ns / interval_ns) * interval_ns;
+        buckets.entry(bucket_start).or_default().push(sample);
+    }
+
+    let mut candles: Vec<Candle> = buckets
+        .into_iter()
+        .map(|(start_ts, bucket)| Candle {
+            open: bucket.first().expect("bucket is never empty").price,
+            close: bucket.last().expect("bucket is never empty").price,
+            high: bucket.iter().map(|s| s.price).fold(f64::MIN, f64::max),
+            low: bucket.iter().map(|s| s.price).fold(f64::MAX, f64::min),
+            start_ts,
+        })
+        .collect();
+
+    if candles.len() > count as usize {
+        let skip = candles.len() - count as usize;
+        candles.drain(0..skip);
+    }
+    candles
+}
+
+/// Save alerts, price series, oracle configuration, cache configuration and OpenChat
+/// configuration to stable storage
+fn save_state(
+    alerts: &Alerts,
+    series: &PriceSeriesMap,
+    oracle_config: &OracleConfig,
+    cache_config: &PriceCacheConfig,
+    openchat_config: &OpenChatConfig,
+) -> Result<(), String> {
+    storage::stable_save((
+        alerts.clone(),
+        series.clone(),
+        oracle_config.clone(),
+        cache_config.clone(),
+        openchat_config.clone(),
+    ))
+    .map_err(|e| format!("Failed to save state: {}", e))
 }
 
 // ===== Public API Methods =====
 
 /// Set a price alert for a specific cryptocurrency
-/// 
+///
 /// # Parameters
 /// * `user` - User identifier (typically an OpenChat principal or username)
 /// * `coin` - Cryptocurrency identifier (e.g., "bitcoin", "ethereum")
 /// * `target_price` - Target price in USD that triggers the alert
-/// 
+/// * `action` - What to do when the target is crossed; defaults to a notification only
+///
 /// # Returns
 /// A confirmation message or error message
 #[update]
-fn set_alert(user: String, coin: String, target_price: f64) -> String {
+fn set_alert(user: String, coin: String, target_price: f64, action: Option<AlertAction>) -> String {
     let mut alerts = load_alerts();
     let key = format!("{}_{}", user, coin);
-    
+
     alerts.insert(
         key.clone(),
         Alert {
             user,
             coin: coin.clone(),
             target_price,
+            action: action.unwrap_or_default(),
+            fired: false,
+            triggered_rising: None,
+            last_delivery: None,
         },
     );
 
-    let prices = load_price_history();
-    match save_state(&alerts, &prices) {
+    let series = load_price_series();
+    let oracle_config = load_oracle_config();
+    let cache_config = load_cache_config();
+    let openchat_config = load_openchat_config();
+    match save_state(&alerts, &series, &oracle_config, &cache_config, &openchat_config) {
         Ok(_) => format!("✅ Alert set for {} when {} reaches ${:.2}", key, coin, target_price),
         Err(e) => format!("❌ Failed to save alert: {}", e),
     }
 }
 
+/// Re-arm a previously fired alert so its action can execute again on the next crossing
+///
+/// # Parameters
+/// * `user` - User identifier the alert belongs to
+/// * `coin` - Cryptocurrency identifier the alert tracks
+#[update]
+fn rearm_alert(user: String, coin: String) -> String {
+    let mut alerts = load_alerts();
+    let key = format!("{}_{}", user, coin);
+
+    let alert = match alerts.get_mut(&key) {
+        Some(alert) => alert,
+        None => return format!("❌ No alert found for {}", key),
+    };
+    alert.fired = false;
+    alert.triggered_rising = None;
+
+    let series = load_price_series();
+    let oracle_config = load_oracle_config();
+    let cache_config = load_cache_config();
+    let openchat_config = load_openchat_config();
+    match save_state(&alerts, &series, &oracle_config, &cache_config, &openchat_config) {
+        Ok(_) => format!("✅ Alert {} re-armed", key),
+        Err(e) => format!("❌ Failed to re-arm alert: {}", e),
+    }
+}
+
 /// Get all registered alerts
 /// 
 /// # Returns
@@ -140,38 +404,51 @@ fn get_alerts() -> Alerts {
 }
 
 /// Check all alerts against current prices and send notifications if needed
+///
+/// # Returns
+/// `Ok(())` if every OpenChat delivery in this pass succeeded, or `Err` describing which
+/// alerts failed to be notified. A delivery failure does not stop the other alerts from
+/// being checked — each alert's own [`DeliveryStatus`] records whether its notification
+/// actually went out.
 #[update]
-async fn check_alerts() {
+async fn check_alerts() -> Result<(), String> {
     api::print(format!("Starting to check alerts..."));
-    let alerts = load_alerts();
+    let mut alerts = load_alerts();
     api::print(format!("Loaded {} alerts", alerts.len()));
-    let mut price_history = load_price_history();
+    let mut price_series = load_price_series();
+    let openchat_config = load_openchat_config();
     let mut updated = false;
+    let mut alerts_updated = false;
+    let mut delivery_errors: Vec<String> = Vec::new();
+
+    // Query the oracle once per distinct coin rather than once per alert
+    let coin_ids: Vec<String> = {
+        let mut ids: Vec<String> = alerts
+            .values()
+            .map(|alert| to_coingecko_id(&alert.coin).to_string())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+    let oracle = PriceOracle::from_config(&load_oracle_config());
+    let prices = oracle.prices(&coin_ids).await;
 
-    for (key, alert) in alerts.iter() {
+    for (key, alert) in alerts.iter_mut() {
         api::print(format!("Checking alert for {}: coin={}, target=${}", key, alert.coin, alert.target_price));
-        
-        // Convert coin name to proper CoinGecko ID
-        let coin_id = match alert.coin.to_lowercase().as_str() {
-            "btc" => "bitcoin",
-            "eth" => "ethereum",
-            "icp" => "internet-computer",
-            "sol" => "solana",
-            // Add more mappings as needed
-            _ => &alert.coin, // Use as-is if no mapping exists
-        };
-        
+
+        let coin_id = to_coingecko_id(&alert.coin);
         api::print(format!("Using CoinGecko ID: {}", coin_id));
-        
-        match get_crypto_price(coin_id).await {
-            Ok(current_price) => {
+
+        match prices.get(coin_id) {
+            Some(&current_price) => {
                 api::print(format!("✅ Got price for {}: ${:.4}", coin_id, current_price));
-                
-                let prev_price = price_history
+
+                let prev_price = price_series
                     .get(coin_id)
-                    .map(|p| p.last_price)
+                    .and_then(|series| series.last_price())
                     .unwrap_or(current_price);
-                
+
                 api::print(format!("Previous price: ${:.4}", prev_price));
 
                 let coingecko_url = format!("https://www.coingecko.com/en/coins/{}", coin_id);
@@ -187,18 +464,20 @@ async fn check_alerts() {
                     )
                 } else {
                     format!(
-                        "ℹ No change in {} price. Current price: ${:.4}. [View on CoinGecko]({})", 
+                        "ℹ No change in {} price. Current price: ${:.4}. [View on CoinGecko]({})",
                         alert.coin, current_price, coingecko_url
                     )
                 };
 
                 // Check if target price is reached
                 let target_message = if prev_price < alert.target_price && current_price >= alert.target_price {
+                    alert.triggered_rising = Some(true);
                     Some(format!(
                         "🎯 Target price alert! **{}** has reached your target of ${:.4}. Current price: ${:.4}. [View on CoinGecko]({})",
                         alert.coin, alert.target_price, current_price, coingecko_url
                     ))
                 } else if prev_price > alert.target_price && current_price <= alert.target_price {
+                    alert.triggered_rising = Some(false);
                     Some(format!(
                         "🎯 Target price alert! **{}** has dropped to your target of ${:.4}. Current price: ${:.4}. [View on CoinGecko]({})",
                         alert.coin, alert.target_price, current_price, coingecko_url
@@ -207,33 +486,94 @@ async fn check_alerts() {
                     None
                 };
 
-                // Send target price message if applicable
-                if let Some(target_msg) = target_message {
+                // Each message sent for this observation shares a target timestamp but gets
+                // its own idempotency key, since a retried call must not dedup distinct messages
+                let target_ts = api::time();
+
+                // Send target price message, if this tick is the crossing itself
+                if let Some(target_msg) = &target_message {
                     api::print(format!("Sending target price alert: {}", target_msg));
-                    send_openchat_message(&alert.user, &target_msg).await;
+                    let idempotency_key = format!("{}:{}:target", key, target_ts);
+                    let status = deliver(&openchat_config, &idempotency_key, &alert.user, target_msg).await;
+                    if !status.success {
+                        delivery_errors.push(format!("{}: {}", key, status.detail));
+                    }
+                    alert.last_delivery = Some(status);
+                    alerts_updated = true;
+                }
+
+                // Whether the price is still on the side of the target that triggered the most
+                // recent crossing — true on the crossing tick itself, and on every later tick
+                // until the price returns back across the target, not just the single tick the
+                // crossing was observed on. This is what lets a failed auto-trade retry instead
+                // of silently never executing again.
+                let past_target = match alert.triggered_rising {
+                    Some(true) => current_price >= alert.target_price,
+                    Some(false) => current_price <= alert.target_price,
+                    None => false,
+                };
+
+                if past_target && !alert.fired {
+                    match apply_alert_action(&alert.action, coin_id, current_price, &alert.user).await {
+                        Ok(Some(summary)) => {
+                            alert.fired = true;
+                            alerts_updated = true;
+                            let confirm = format!("✅ Auto-trade executed for {}: {}", alert.user, summary);
+                            api::print(confirm.clone());
+                            let idempotency_key = format!("{}:{}:trade", key, target_ts);
+                            let status = deliver(&openchat_config, &idempotency_key, &alert.user, &confirm).await;
+                            if !status.success {
+                                delivery_errors.push(format!("{}: {}", key, status.detail));
+                            }
+                        }
+                        Ok(None) => {} // Notify-only action; nothing further to record
+                        Err(e) => {
+                            let err_msg = format!("❌ Auto-trade failed for {}: {}", alert.user, e);
+                            api::print(err_msg.clone());
+                            let idempotency_key = format!("{}:{}:trade-failed", key, target_ts);
+                            let status = deliver(&openchat_config, &idempotency_key, &alert.user, &err_msg).await;
+                            if !status.success {
+                                delivery_errors.push(format!("{}: {}", key, status.detail));
+                            }
+                        }
+                    }
                 }
 
                 // Send regular price update message
                 api::print(format!("Sending price update: {}", message));
-                send_openchat_message(&alert.user, &message).await;
-                
-                // Update price history
-                price_history.insert(coin_id.to_string(), PriceHistory { last_price: current_price });
+                let idempotency_key = format!("{}:{}:price", key, target_ts);
+                let status = deliver(&openchat_config, &idempotency_key, &alert.user, &message).await;
+                if !status.success {
+                    delivery_errors.push(format!("{}: {}", key, status.detail));
+                }
+                alert.last_delivery = Some(status);
+                alerts_updated = true;
+
+                // Record the new sample in this coin's price series
+                price_series.entry(coin_id.to_string()).or_default().push(current_price);
                 updated = true;
                 api::print(format!("Updated price history for {}", coin_id));
             }
-            Err(e) => api::print(format!("❌ Error fetching price for {}: {}", coin_id, e)),
+            None => api::print(format!("❌ No price returned for {}", coin_id)),
         }
     }
 
-    if updated {
-        match save_state(&alerts, &price_history) {
+    if updated || alerts_updated {
+        let oracle_config = load_oracle_config();
+        let cache_config = load_cache_config();
+        match save_state(&alerts, &price_series, &oracle_config, &cache_config, &openchat_config) {
             Ok(_) => api::print(format!("✅ Successfully saved price history")),
             Err(e) => api::print(format!("❌ Failed to save price history: {}", e)),
         }
     } else {
         api::print(format!("ℹ No price updates to save"));
     }
+
+    if delivery_errors.is_empty() {
+        Ok(())
+    } else {
+        Err(delivery_errors.join("; "))
+    }
 }
 
 /// Get the current price of Internet Computer (ICP) token
@@ -347,8 +687,97 @@ fn transform(raw: TransformArgs) -> HttpResponse {
 
 // ===== Helper Functions =====
 
+/// Convert a coin symbol to its CoinGecko ID
+fn to_coingecko_id(coin: &str) -> &str {
+    match coin.to_lowercase().as_str() {
+        "btc" => "bitcoin",
+        "eth" => "ethereum",
+        "icp" => "internet-computer",
+        "sol" => "solana",
+        // Add more mappings as needed
+        _ => coin, // Use as-is if no mapping exists
+    }
+}
+
 /// Fetch the current price of a cryptocurrency from CoinGecko
+///
+/// Reuses a cached price if one was fetched within the configured TTL, and retries
+/// a 429/5xx response with exponential backoff before falling back to a stale cache
+/// entry (if one exists) rather than failing the caller outright.
 async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
+    let config = load_cache_config();
+    let now = api::time();
+
+    if let Some(price) = cached_price(coin_id, now, config.ttl_ns) {
+        return Ok(price);
+    }
+
+    let mut backoff_secs = 1u64;
+    let mut last_err = String::new();
+    for attempt in 0..=config.max_retries {
+        match fetch_crypto_price_once(coin_id).await {
+            Ok(price) => {
+                PRICE_CACHE.with(|cache| cache.borrow_mut().insert(coin_id.to_string(), (price, now)));
+                return Ok(price);
+            }
+            Err((status, msg)) => {
+                last_err = msg;
+                if is_retryable_status(&status) && attempt < config.max_retries {
+                    api::print(format!(
+                        "⚠️ {} returned a retryable error (attempt {}/{}), backing off {}s: {}",
+                        coin_id, attempt + 1, config.max_retries, backoff_secs, last_err
+                    ));
+                    sleep_secs(backoff_secs).await;
+                    backoff_secs *= 2;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Retries exhausted (or a non-retryable error): fall back to a cached value if it isn't too stale
+    let stale_ttl_ns = config.ttl_ns.saturating_mul(10);
+    if let Some(price) = cached_price(coin_id, now, stale_ttl_ns) {
+        api::print(format!("⚠️ Falling back to cached price for {} after: {}", coin_id, last_err));
+        return Ok(price);
+    }
+
+    Err(last_err)
+}
+
+/// Look up a cached price for `coin_id`, returning `None` if absent or older than `ttl_ns`
+fn cached_price(coin_id: &str, now: u64, ttl_ns: u64) -> Option<f64> {
+    PRICE_CACHE.with(|cache| {
+        cache.borrow().get(coin_id).and_then(|(price, fetched_at)| {
+            if now.saturating_sub(*fetched_at) < ttl_ns {
+                Some(*price)
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Suspend the current call for `secs` seconds using a one-shot IC timer
+async fn sleep_secs(secs: u64) {
+    let (tx, rx) = futures::channel::oneshot::channel::<()>();
+    ic_cdk_timers::set_timer(Duration::from_secs(secs), move || {
+        let _ = tx.send(());
+    });
+    let _ = rx.await;
+}
+
+/// Whether a response status should be retried (429 rate-limit or any 5xx)
+fn is_retryable_status(status: &Option<candid::Nat>) -> bool {
+    match status {
+        Some(s) => *s == candid::Nat::from(429u64) || (*s >= candid::Nat::from(500u64) && *s < candid::Nat::from(600u64)),
+        None => false,
+    }
+}
+
+/// One raw HTTP attempt against CoinGecko's `/coins/{id}` endpoint
+async fn fetch_crypto_price_once(coin_id: &str) -> Result<f64, (Option<candid::Nat>, String)> {
     let host = "api.coingecko.com";
     let url = format!(
         "https://{}/api/v3/coins/{}?localization=false&tickers=false&market_data=true&community_data=false&developer_data=false&sparkline=false",
@@ -365,7 +794,7 @@ async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
             value: "IC-Canister".to_string(),
         },
     ];
-            
+
     let req = CanisterHttpRequestArgument {
         url,
         method: HttpMethod::GET,
@@ -377,6 +806,96 @@ async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
 
     let cycles: u128 = 1_603_146_400 + 10_000; // Add buffer to ensure enough cycles
 
+    match http_request(req, cycles).await {
+        Ok((response,)) => {
+            if response.status != candid::Nat::from(200u64) {
+                let error_body = String::from_utf8(response.body.clone())
+                    .unwrap_or_else(|_| format!("Non-UTF8 error response: {:?}", response.body));
+                return Err((Some(response.status.clone()), format!("API error (status {}): {}", response.status, error_body)));
+            }
+
+            let str_body = String::from_utf8(response.body)
+                .map_err(|e| (None, format!("Invalid UTF-8 in response: {}", e)))?;
+
+            let json: Value = serde_json::from_str(&str_body)
+                .map_err(|e| (None, format!("Failed to parse JSON: {}", e)))?;
+
+            json["market_data"]["current_price"]["usd"]
+                .as_f64()
+                .ok_or_else(|| (None, "Price data not found in response".to_string()))
+        }
+        Err((r, m)) => Err((None, format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m))),
+    }
+}
+
+/// Fetch current USD prices for many coins in a single CoinGecko outcall
+///
+/// Coins with a fresh cache entry (per the same TTL [`get_crypto_price`] uses) are served from
+/// cache; the rest are fetched together via one `/simple/price` request instead of one
+/// `/coins/{id}` outcall per coin. Coins that still can't be priced are omitted from the result.
+async fn get_crypto_prices(coin_ids: &[String]) -> HashMap<String, f64> {
+    let config = load_cache_config();
+    let now = api::time();
+
+    let mut prices = HashMap::new();
+    let mut missing = Vec::new();
+    for coin_id in coin_ids {
+        match cached_price(coin_id, now, config.ttl_ns) {
+            Some(price) => {
+                prices.insert(coin_id.clone(), price);
+            }
+            None => missing.push(coin_id.clone()),
+        }
+    }
+
+    if missing.is_empty() {
+        return prices;
+    }
+
+    match fetch_crypto_prices_once(&missing).await {
+        Ok(fetched) => {
+            for (coin_id, price) in fetched {
+                PRICE_CACHE.with(|cache| cache.borrow_mut().insert(coin_id.clone(), (price, now)));
+                prices.insert(coin_id, price);
+            }
+        }
+        Err(e) => api::print(format!("❌ Batched price request failed for {:?}: {}", missing, e)),
+    }
+
+    prices
+}
+
+/// One raw HTTP attempt against CoinGecko's batched `/simple/price` endpoint
+async fn fetch_crypto_prices_once(coin_ids: &[String]) -> Result<HashMap<String, f64>, String> {
+    let host = "api.coingecko.com";
+    let url = format!(
+        "https://{}/api/v3/simple/price?ids={}&vs_currencies=usd",
+        host,
+        coin_ids.join(",")
+    );
+
+    let req_headers = vec![
+        HttpHeader {
+            name: "Accept".to_string(),
+            value: "application/json".to_string(),
+        },
+        HttpHeader {
+            name: "User-Agent".to_string(),
+            value: "IC-Canister".to_string(),
+        },
+    ];
+
+    let req = CanisterHttpRequestArgument {
+        url,
+        method: HttpMethod::GET,
+        body: None,
+        max_response_bytes: Some(8_192), // tiny {id: {usd: price}} map, a few KB is plenty
+        transform: Some(TransformContext::from_name("transform".to_string(), vec![])),
+        headers: req_headers,
+    };
+
+    let cycles: u128 = 1_603_146_400 + 10_000;
+
     match http_request(req, cycles).await {
         Ok((response,)) => {
             if response.status != candid::Nat::from(200u64) {
@@ -384,61 +903,552 @@ async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
                     .unwrap_or_else(|_| format!("Non-UTF8 error response: {:?}", response.body));
                 return Err(format!("API error (status {}): {}", response.status, error_body));
             }
-            
+
             let str_body = String::from_utf8(response.body)
                 .map_err(|e| format!("Invalid UTF-8 in response: {}", e))?;
-            
+
             let json: Value = serde_json::from_str(&str_body)
                 .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-            
-            json["market_data"]["current_price"]["usd"]
-                .as_f64()
-                .ok_or_else(|| "Price data not found in response".to_string())
+
+            Ok(coin_ids
+                .iter()
+                .filter_map(|id| json[id]["usd"].as_f64().map(|price| (id.clone(), price)))
+                .collect())
         }
         Err((r, m)) => Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m)),
     }
 }
 
-/// Get the current price of any cryptocurrency supported by CoinGecko
-/// 
+/// Get the current price of any cryptocurrency supported by the price oracle
+///
 /// # Parameters
 /// * `coin_id` - The CoinGecko ID of the cryptocurrency (e.g., "bitcoin", "ethereum", "internet-computer")
-/// 
+///
 /// # Returns
 /// The current price as a string with USD symbol or an error message
 #[update]
 async fn get_crypto_price_api(coin_id: String) -> String {
-    match get_crypto_price(&coin_id).await {
+    let oracle = PriceOracle::from_config(&load_oracle_config());
+    match oracle.price(&coin_id).await {
         Ok(price) => format!("${:.4}", price),
         Err(e) => format!("Error: {}", e)
     }
 }
 
-/// Send a message to a user via OpenChat
-async fn send_openchat_message(user_id: &str, message: &str) {
-    // This is a placeholder for the actual implementation
-    // In a real implementation, you would make an HTTP request to OpenChat's API
+// ===== Alert-Triggered Trading =====
+
+/// Execute an [`AlertAction`] against the user's portfolio using the price that was already
+/// observed for this crossing (rather than re-fetching, since the alert and the trade must
+/// agree on the price that triggered it)
+///
+/// # Returns
+/// `Ok(Some(summary))` if a trade was executed, `Ok(None)` for [`AlertAction::Notify`], or
+/// `Err` describing why the trade could not be executed
+async fn apply_alert_action(
+    action: &AlertAction,
+    coin_id: &str,
+    price: f64,
+    user_id: &str,
+) -> Result<Option<String>, String> {
+    match action {
+        AlertAction::Notify => Ok(None),
+        AlertAction::Buy { quote_amount_usd } => {
+            buy_into_portfolio(user_id, coin_id, price, *quote_amount_usd).map(Some)
+        }
+        AlertAction::SellAll => sell_from_portfolio(user_id, coin_id, price, 100.0).map(Some),
+        AlertAction::SellFraction { pct } => sell_from_portfolio(user_id, coin_id, price, *pct).map(Some),
+    }
+}
+
+/// Spend `amount_usd` of the user's USD balance on `coin_id` at `price`
+fn buy_into_portfolio(user_id: &str, coin_id: &str, price: f64, amount_usd: f64) -> Result<String, String> {
+    let price = Decimal::from_f64(price).ok_or_else(|| "Invalid price".to_string())?;
+    let amount_usd = Decimal::from_f64(amount_usd).ok_or_else(|| "Invalid USD amount".to_string())?;
+
+    let mut portfolios = crate::trading::load_portfolios();
+    let portfolio = portfolios
+        .get_mut(user_id)
+        .ok_or_else(|| format!("Portfolio not found for user {}", user_id))?;
+
+    let crypto_amount = crate::trading::execute_buy(portfolio, coin_id, price, amount_usd)?;
+
+    crate::trading::save_portfolios(&portfolios)?;
+    Ok(format!("bought {} {} for ${} at ${}", crypto_amount, coin_id, amount_usd, price))
+}
+
+/// Sell `pct` percent (0-100) of the user's holding of `coin_id` at `price`
+fn sell_from_portfolio(user_id: &str, coin_id: &str, price: f64, pct: f64) -> Result<String, String> {
+    let price = Decimal::from_f64(price).ok_or_else(|| "Invalid price".to_string())?;
+    let pct = Decimal::from_f64(pct.clamp(0.0, 100.0)).ok_or_else(|| "Invalid percentage".to_string())?;
+
+    let mut portfolios = crate::trading::load_portfolios();
+    let portfolio = portfolios
+        .get_mut(user_id)
+        .ok_or_else(|| format!("Portfolio not found for user {}", user_id))?;
+
+    let held = match portfolio.holdings.get(coin_id) {
+        Some(existing) => crate::trading::parse_decimal(existing)?,
+        None => Decimal::ZERO,
+    };
+    let sell_amount = crate::trading::checked_mul(held, crate::trading::checked_div(pct, Decimal::ONE_HUNDRED)?)?;
+    if sell_amount <= Decimal::ZERO {
+        return Err(format!("No {} holding to sell for user {}", coin_id, user_id));
+    }
+
+    let usd_value = crate::trading::execute_sell(portfolio, coin_id, price, sell_amount)?;
+
+    crate::trading::save_portfolios(&portfolios)?;
+    Ok(format!("sold {} {} for ${} at ${}", sell_amount, coin_id, usd_value, price))
+}
+
+// ===== Price Oracle =====
+
+/// One upstream feed the [`PriceOracle`] can query for a coin's USD price
+trait PriceSource {
+    /// Short name used in logs and source-selection config
+    fn name(&self) -> &'static str;
+
+    /// Fetch the current USD price of `coin_id` from this source
+    fn fetch<'a>(&'a self, coin_id: &'a str) -> Pin<Box<dyn Future<Output = Result<f64, String>> + 'a>>;
+
+    /// Fetch current USD prices for several coins at once
+    ///
+    /// Sources that can't batch fall back to one `fetch` per coin; sources that expose a
+    /// batched endpoint (e.g. [`CoinGeckoSource`]) should override this to issue a single
+    /// outcall instead. Coins this source couldn't price are simply omitted from the result.
+    fn fetch_many<'a>(&'a self, coin_ids: &'a [String]) -> Pin<Box<dyn Future<Output = HashMap<String, f64>> + 'a>> {
+        Box::pin(async move {
+            let mut prices = HashMap::new();
+            for coin_id in coin_ids {
+                match self.fetch(coin_id).await {
+                    Ok(price) => {
+                        prices.insert(coin_id.clone(), price);
+                    }
+                    Err(e) => api::print(format!("⚠️ Price source '{}' failed for {}: {}", self.name(), coin_id, e)),
+                }
+            }
+            prices
+        })
+    }
+}
+
+/// Queries CoinGecko's `/coins/{id}` endpoint
+struct CoinGeckoSource;
+
+impl PriceSource for CoinGeckoSource {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn fetch<'a>(&'a self, coin_id: &'a str) -> Pin<Box<dyn Future<Output = Result<f64, String>> + 'a>> {
+        Box::pin(get_crypto_price(coin_id))
+    }
+
+    fn fetch_many<'a>(&'a self, coin_ids: &'a [String]) -> Pin<Box<dyn Future<Output = HashMap<String, f64>> + 'a>> {
+        Box::pin(get_crypto_prices(coin_ids))
+    }
+}
+
+/// Queries CoinMarketCap's `/v2/cryptocurrency/quotes/latest` endpoint
+struct CoinMarketCapSource {
+    api_key: String,
+}
+
+impl PriceSource for CoinMarketCapSource {
+    fn name(&self) -> &'static str {
+        "coinmarketcap"
+    }
+
+    fn fetch<'a>(&'a self, coin_id: &'a str) -> Pin<Box<dyn Future<Output = Result<f64, String>> + 'a>> {
+        Box::pin(fetch_cmc_price(coin_id, &self.api_key))
+    }
+}
+
+/// Fetch a coin's USD price from CoinMarketCap
+async fn fetch_cmc_price(coin_id: &str, api_key: &str) -> Result<f64, String> {
+    let symbol = to_cmc_symbol(coin_id);
+    let host = "pro-api.coinmarketcap.com";
+    let url = format!("https://{}/v2/cryptocurrency/quotes/latest?symbol={}", host, symbol);
+
+    let req_headers = vec![
+        HttpHeader {
+            name: "Accept".to_string(),
+            value: "application/json".to_string(),
+        },
+        HttpHeader {
+            name: "X-CMC_PRO_API_KEY".to_string(),
+            value: api_key.to_string(),
+        },
+    ];
+
+    let req = CanisterHttpRequestArgument {
+        url,
+        method: HttpMethod::GET,
+        body: None,
+        max_response_bytes: Some(16_384),
+        transform: Some(TransformContext::from_name("transform".to_string(), vec![])),
+        headers: req_headers,
+    };
+
+    let cycles: u128 = 1_603_146_400 + 10_000;
+
+    match http_request(req, cycles).await {
+        Ok((response,)) => {
+            if response.status != candid::Nat::from(200u64) {
+                let error_body = String::from_utf8(response.body.clone())
+                    .unwrap_or_else(|_| format!("Non-UTF8 error response: {:?}", response.body));
+                return Err(format!("CoinMarketCap API error (status {}): {}", response.status, error_body));
+            }
+
+            let str_body = String::from_utf8(response.body)
+                .map_err(|e| format!("Invalid UTF-8 in CoinMarketCap response: {}", e))?;
+
+            let json: Value = serde_json::from_str(&str_body)
+                .map_err(|e| format!("Failed to parse CoinMarketCap JSON: {}", e))?;
+
+            json["data"][symbol]["quote"]["USD"]["price"]
+                .as_f64()
+                .ok_or_else(|| "Price data not found in CoinMarketCap response".to_string())
+        }
+        Err((r, m)) => Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m)),
+    }
+}
+
+/// Map a CoinGecko ID to the ticker symbol CoinMarketCap expects
+fn to_cmc_symbol(coin_id: &str) -> &str {
+    match coin_id {
+        "bitcoin" => "BTC",
+        "ethereum" => "ETH",
+        "internet-computer" => "ICP",
+        "solana" => "SOL",
+        other => other,
+    }
+}
+
+/// Always returns a configured constant price; useful for integration tests
+struct ForcedPriceSource {
+    price: f64,
+}
+
+impl PriceSource for ForcedPriceSource {
+    fn name(&self) -> &'static str {
+        "forced"
+    }
+
+    fn fetch<'a>(&'a self, _coin_id: &'a str) -> Pin<Box<dyn Future<Output = Result<f64, String>> + 'a>> {
+        let price = self.price;
+        Box::pin(async move { Ok(price) })
+    }
+}
+
+/// Never returns a price; a disabled placeholder slot in the source list
+struct NoOpSource;
+
+impl PriceSource for NoOpSource {
+    fn name(&self) -> &'static str {
+        "noop"
+    }
+
+    fn fetch<'a>(&'a self, _coin_id: &'a str) -> Pin<Box<dyn Future<Output = Result<f64, String>> + 'a>> {
+        Box::pin(async { Err("NoOpSource never returns a price".to_string()) })
+    }
+}
+
+/// Which [`PriceSource`] a [`SourceKind`] entry in stable storage should build
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq)]
+enum SourceKind {
+    CoinGecko,
+    CoinMarketCap,
+    Forced { price: f64 },
+    NoOp,
+}
+
+/// Persisted configuration for the multi-source price oracle
+#[derive(Clone, Debug, CandidType, Deserialize)]
+struct OracleConfig {
+    /// Ordered list of sources to query, highest priority first
+    sources: Vec<SourceKind>,
+    /// CoinMarketCap API key, sent as the `X-CMC_PRO_API_KEY` header
+    cmc_api_key: String,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![SourceKind::CoinGecko],
+            cmc_api_key: String::new(),
+        }
+    }
+}
+
+/// Queries an ordered set of [`PriceSource`]s and aggregates the results
+struct PriceOracle {
+    sources: Vec<Box<dyn PriceSource>>,
+}
+
+impl PriceOracle {
+    /// Build the boxed sources described by a persisted [`OracleConfig`]
+    fn from_config(config: &OracleConfig) -> Self {
+        let sources = config
+            .sources
+            .iter()
+            .map(|kind| -> Box<dyn PriceSource> {
+                match kind {
+                    SourceKind::CoinGecko => Box::new(CoinGeckoSource),
+                    SourceKind::CoinMarketCap => Box::new(CoinMarketCapSource {
+                        api_key: config.cmc_api_key.clone(),
+                    }),
+                    SourceKind::Forced { price } => Box::new(ForcedPriceSource { price: *price }),
+                    SourceKind::NoOp => Box::new(NoOpSource),
+                }
+            })
+            .collect();
+        Self { sources }
+    }
+
+    /// Query every configured source and return the median of the surviving prices
+    async fn price(&self, coin_id: &str) -> Result<f64, String> {
+        let mut prices = Vec::new();
+        for source in &self.sources {
+            match source.fetch(coin_id).await {
+                Ok(price) => prices.push(price),
+                Err(e) => api::print(format!("⚠️ Price source '{}' failed for {}: {}", source.name(), coin_id, e)),
+            }
+        }
+
+        if prices.is_empty() {
+            return Err(format!("All price sources failed for {}", coin_id));
+        }
+
+        // A caller-supplied `Forced` price is validated finite in `set_price_sources`, but
+        // `total_cmp` is used here too so a NaN slipping through any source can't panic the sort
+        prices.sort_by(|a, b| a.total_cmp(b));
+        let mid = prices.len() / 2;
+        let median = if prices.len() % 2 == 0 {
+            (prices[mid - 1] + prices[mid]) / 2.0
+        } else {
+            prices[mid]
+        };
+        Ok(median)
+    }
+
+    /// Query every configured source for many coins in one pass and return each coin's median
+    ///
+    /// Sources that support batching (like [`CoinGeckoSource`]) issue a single outcall for all
+    /// of `coin_ids` instead of one outcall per coin, so callers pricing many coins (e.g.
+    /// [`check_alerts`]) should prefer this over looping [`Self::price`].
+    async fn prices(&self, coin_ids: &[String]) -> HashMap<String, f64> {
+        let mut per_coin: HashMap<String, Vec<f64>> = HashMap::new();
+        for source in &self.sources {
+            for (coin_id, price) in source.fetch_many(coin_ids).await {
+                per_coin.entry(coin_id).or_default().push(price);
+            }
+        }
+
+        per_coin
+            .into_iter()
+            .filter_map(|(coin_id, mut values)| {
+                if values.is_empty() {
+                    return None;
+                }
+                values.sort_by(|a, b| a.total_cmp(b));
+                let mid = values.len() / 2;
+                let median = if values.len() % 2 == 0 {
+                    (values[mid - 1] + values[mid]) / 2.0
+                } else {
+                    values[mid]
+                };
+                Some((coin_id, median))
+            })
+            .collect()
+    }
+}
+
+/// Reject a call from anyone but a controller of this canister
+///
+/// Used to gate admin-only configuration updates (oracle sources, API keys, OpenChat token)
+/// that would otherwise let any caller manipulate prices or messaging for every user.
+fn require_controller() -> Result<(), String> {
+    if api::is_controller(&api::caller()) {
+        Ok(())
+    } else {
+        Err("❌ Unauthorized: caller is not a controller of this canister".to_string())
+    }
+}
+
+/// Replace the ordered list of price sources the oracle queries
+///
+/// # Parameters
+/// * `sources` - New ordered list of sources, highest priority first
+#[update]
+fn set_price_sources(sources: Vec<SourceKind>) -> String {
+    if let Err(e) = require_controller() {
+        return e;
+    }
+
+    if let Some(price) = sources.iter().find_map(|s| match s {
+        SourceKind::Forced { price } if !price.is_finite() => Some(*price),
+        _ => None,
+    }) {
+        return format!("❌ Forced price must be finite, got {}", price);
+    }
+
+    let alerts = load_alerts();
+    let series = load_price_series();
+    let mut config = load_oracle_config();
+    config.sources = sources;
+    let cache_config = load_cache_config();
+    let openchat_config = load_openchat_config();
+    match save_state(&alerts, &series, &config, &cache_config, &openchat_config) {
+        Ok(_) => format!("✅ Price sources updated: {} source(s) configured", config.sources.len()),
+        Err(e) => format!("❌ Failed to save price sources: {}", e),
+    }
+}
+
+/// Set the CoinMarketCap API key used by [`CoinMarketCapSource`]
+#[update]
+fn set_cmc_api_key(api_key: String) -> String {
+    if let Err(e) = require_controller() {
+        return e;
+    }
+
+    let alerts = load_alerts();
+    let series = load_price_series();
+    let mut config = load_oracle_config();
+    config.cmc_api_key = api_key;
+    let cache_config = load_cache_config();
+    let openchat_config = load_openchat_config();
+    match save_state(&alerts, &series, &config, &cache_config, &openchat_config) {
+        Ok(_) => "✅ CoinMarketCap API key updated".to_string(),
+        Err(e) => format!("❌ Failed to save CoinMarketCap API key: {}", e),
+    }
+}
+
+/// Get the currently configured price sources (the CoinMarketCap API key is withheld)
+#[query]
+fn get_price_sources() -> Vec<SourceKind> {
+    load_oracle_config().sources
+}
+
+/// Configure the short-lived price cache TTL and the retry count for 429/5xx responses
+///
+/// # Parameters
+/// * `ttl_secs` - How long a cached price is considered fresh, in seconds
+/// * `max_retries` - Number of retries on a 429/5xx response before giving up
+#[update]
+fn set_price_cache_config(ttl_secs: u64, max_retries: u32) -> String {
+    if let Err(e) = require_controller() {
+        return e;
+    }
+
+    let alerts = load_alerts();
+    let series = load_price_series();
+    let oracle_config = load_oracle_config();
+    let cache_config = PriceCacheConfig {
+        ttl_ns: ttl_secs.saturating_mul(1_000_000_000),
+        max_retries,
+    };
+    let openchat_config = load_openchat_config();
+    match save_state(&alerts, &series, &oracle_config, &cache_config, &openchat_config) {
+        Ok(_) => format!("✅ Price cache TTL set to {}s with {} retries", ttl_secs, max_retries),
+        Err(e) => format!("❌ Failed to save price cache config: {}", e),
+    }
+}
+
+/// Set the bearer token sent as `Authorization: Bearer <token>` on OpenChat outcalls
+#[update]
+fn set_openchat_token(bearer_token: String) -> String {
+    if let Err(e) = require_controller() {
+        return e;
+    }
+
+    let alerts = load_alerts();
+    let series = load_price_series();
+    let oracle_config = load_oracle_config();
+    let cache_config = load_cache_config();
+    let openchat_config = OpenChatConfig { bearer_token };
+    match save_state(&alerts, &series, &oracle_config, &cache_config, &openchat_config) {
+        Ok(_) => "✅ OpenChat bearer token updated".to_string(),
+        Err(e) => format!("❌ Failed to save OpenChat bearer token: {}", e),
+    }
+}
+
+/// Send a message to a user via OpenChat and record the outcome as a [`DeliveryStatus`]
+///
+/// Never fails the caller: a transport or non-2xx error is folded into the returned status
+/// so callers can record per-alert delivery history instead of losing the failure.
+async fn deliver(config: &OpenChatConfig, idempotency_key: &str, user_id: &str, message: &str) -> DeliveryStatus {
+    let (success, detail) = match send_openchat_message(config, idempotency_key, user_id, message).await {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, e),
+    };
+    DeliveryStatus {
+        timestamp_ns: api::time(),
+        success,
+        detail,
+    }
+}
+
+/// Post a message to the OpenChat bot messaging endpoint
+///
+/// # Returns
+/// `Ok(detail)` with the response body on a 2xx response, or `Err` describing the transport
+/// or HTTP failure
+async fn send_openchat_message(
+    config: &OpenChatConfig,
+    idempotency_key: &str,
+    user_id: &str,
+    message: &str,
+) -> Result<String, String> {
     api::print(format!("📨 Sending message to {}: {}", user_id, message));
-    
-    // TODO: Implement actual OpenChat integration
-    // Example implementation would look like:
-    // let openchat_url = "https://api.openchat.com/messages";
-    // let request_body = json!({
-    //     "recipient": user_id,
-    //     "message": message,
-    //     "format": "markdown"
-    // }).to_string().into_bytes();
-    // 
-    // let req = CanisterHttpRequestArgument {
-    //     url: openchat_url.to_string(),
-    //     method: HttpMethod::POST,
-    //     body: Some(request_body),
-    //     ...
-    // };
-    // 
-    // match http_request(req, cycles).await {
-    //     ...
-    // }
+
+    let body = serde_json::json!({
+        "recipient": user_id,
+        "message": message,
+        "format": "markdown",
+        "idempotency_key": idempotency_key,
+    })
+    .to_string()
+    .into_bytes();
+
+    let req_headers = vec![
+        HttpHeader {
+            name: "Content-Type".to_string(),
+            value: "application/json".to_string(),
+        },
+        HttpHeader {
+            name: "Authorization".to_string(),
+            value: format!("Bearer {}", config.bearer_token),
+        },
+        HttpHeader {
+            name: "Idempotency-Key".to_string(),
+            value: idempotency_key.to_string(),
+        },
+    ];
+
+    let req = CanisterHttpRequestArgument {
+        url: "https://api.openchat.com/bot/messages".to_string(),
+        method: HttpMethod::POST,
+        body: Some(body),
+        max_response_bytes: Some(16_384),
+        transform: Some(TransformContext::from_name("transform".to_string(), vec![])),
+        headers: req_headers,
+    };
+
+    let cycles: u128 = 1_603_146_400 + 10_000;
+
+    match http_request(req, cycles).await {
+        Ok((response,)) => {
+            let str_body = String::from_utf8(response.body)
+                .unwrap_or_else(|_| "<non-UTF8 response body>".to_string());
+            if response.status >= candid::Nat::from(200u64) && response.status < candid::Nat::from(300u64) {
+                Ok(str_body)
+            } else {
+                Err(format!("OpenChat API error (status {}): {}", response.status, str_body))
+            }
+        }
+        Err((r, m)) => Err(format!("HTTP request to OpenChat failed. RejectionCode: {:?}, Error: {}", r, m)),
+    }
 }
 
 // Generate Candid interface
@@ -1,5 +1,5 @@
 //! # Cryptocurrency Trading Module
-//! 
+//!
 //! This module provides functionality for simulated cryptocurrency trading:
 //! - Get current market prices from CoinGecko
 //! - Manage user portfolios and balances
@@ -13,20 +13,41 @@ use ic_cdk::{
     },
     api, storage, update, query,
 };
+use rust_decimal::Decimal;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+/// Below this remainder a lot or holding is treated as fully consumed, mirroring the dust
+/// threshold `f64` balances used before the move to `Decimal`
+pub(crate) const DUST_THRESHOLD: Decimal = Decimal::new(1, 6);
 
 // ===== Data Structures =====
 
 /// Represents a user's portfolio of cryptocurrencies
-#[derive(Clone, Debug, CandidType, Deserialize, Default)]
+///
+/// All money and quantity fields are decimal strings (e.g. `"1234.56"`) rather than `f64`, so
+/// repeated buys/sells don't accumulate floating-point rounding error. Use [`parse_decimal`] to
+/// read them and `Decimal::to_string()` to write them back.
+#[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct Portfolio {
-    /// User's USD balance
-    pub usd_balance: f64,
-    /// Map of cryptocurrency ID to amount owned
-    pub holdings: HashMap<String, f64>,
+    /// User's USD balance, as a decimal string
+    pub usd_balance: String,
+    /// Map of cryptocurrency ID to amount owned, as decimal strings
+    pub holdings: HashMap<String, String>,
     /// Transaction history
     pub transactions: Vec<Transaction>,
+    /// Open FIFO cost-basis lots per coin, oldest first; consumed front-to-back on sell
+    pub lots: HashMap<String, VecDeque<Lot>>,
+}
+
+/// A single open buy lot used for FIFO cost-basis accounting
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct Lot {
+    /// Amount of the coin still unsold from this lot, as a decimal string
+    pub amount_remaining: String,
+    /// USD price per unit paid when this lot was bought, as a decimal string
+    pub price_paid: String,
 }
 
 /// Represents a buy or sell transaction
@@ -36,14 +57,16 @@ pub struct Transaction {
     pub transaction_type: TransactionType,
     /// Cryptocurrency ID
     pub coin_id: String,
-    /// Amount of cryptocurrency
-    pub amount: f64,
-    /// Price per unit in USD
-    pub price: f64,
-    /// Total value of transaction in USD
-    pub total_value: f64,
+    /// Amount of cryptocurrency, as a decimal string
+    pub amount: String,
+    /// Price per unit in USD, as a decimal string
+    pub price: String,
+    /// Total value of transaction in USD, as a decimal string
+    pub total_value: String,
     /// Timestamp of transaction
     pub timestamp: u64,
+    /// Realized gain/loss in USD versus FIFO cost basis, as a decimal string; always "0" for buys
+    pub realized_pnl: String,
 }
 
 /// Type of transaction
@@ -53,85 +76,154 @@ pub enum TransactionType {
     Sell,
 }
 
+/// A pending order to buy or sell at a future market price
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct LimitOrder {
+    /// Unique, monotonically-assigned order identifier
+    pub id: String,
+    /// User identifier
+    pub user_id: String,
+    /// CoinGecko cryptocurrency ID
+    pub coin_id: String,
+    /// Whether this order buys or sells `coin_id` once it fills
+    pub side: TransactionType,
+    /// Market price, as a decimal string, that triggers the order
+    pub target_price: String,
+    /// Whether the order fills when the market price rises to or above `target_price`, or
+    /// falls to or below it
+    pub trigger: OrderTrigger,
+    /// For a buy: USD amount to spend. For a sell: amount of `coin_id` to sell. Decimal string
+    pub amount: String,
+}
+
+/// Which side of `target_price` fills a [`LimitOrder`]
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq)]
+pub enum OrderTrigger {
+    /// Fills once the market price is greater than or equal to `target_price`
+    Above,
+    /// Fills once the market price is less than or equal to `target_price`
+    Below,
+}
+
 /// Type alias for user portfolios storage
 type Portfolios = HashMap<String, Portfolio>;
 
+/// Type alias for open limit-order storage, keyed by [`LimitOrder::id`]
+type Orders = HashMap<String, LimitOrder>;
+
 // ===== Storage Management =====
 
+/// Combined stable storage state: portfolios, open orders, and the next order ID to assign
+type TradingState = (Portfolios, Orders, u64);
+
 /// Load user portfolios from stable storage
-fn load_portfolios() -> Portfolios {
-    match storage::stable_restore::<(Portfolios,)>() {
-        Ok((portfolios,)) => portfolios,
-        Err(e) => {
+pub(crate) fn load_portfolios() -> Portfolios {
+    storage::stable_restore::<TradingState>()
+        .map(|(portfolios, _, _)| portfolios)
+        .unwrap_or_else(|e| {
             api::print(format!("⚠️ Failed to load portfolios: {}", e));
             HashMap::new()
-        }
-    }
+        })
+}
+
+/// Load open limit orders from stable storage
+fn load_orders() -> Orders {
+    storage::stable_restore::<TradingState>()
+        .map(|(_, orders, _)| orders)
+        .unwrap_or_else(|e| {
+            api::print(format!("⚠️ Failed to load orders: {}", e));
+            HashMap::new()
+        })
+}
+
+/// Load the next limit-order ID to assign from stable storage
+fn load_next_order_id() -> u64 {
+    storage::stable_restore::<TradingState>()
+        .map(|(_, _, next_id)| next_id)
+        .unwrap_or(0)
+}
+
+/// Save user portfolios to stable storage, leaving open orders and the order ID counter
+/// untouched
+pub(crate) fn save_portfolios(portfolios: &Portfolios) -> Result<(), String> {
+    let orders = load_orders();
+    let next_order_id = load_next_order_id();
+    save_state(portfolios, &orders, next_order_id)
+}
+
+/// Save open limit orders and the next order ID to assign, leaving portfolios untouched
+fn save_orders(orders: &Orders, next_order_id: u64) -> Result<(), String> {
+    let portfolios = load_portfolios();
+    save_state(&portfolios, orders, next_order_id)
 }
 
-/// Save user portfolios to stable storage
-fn save_portfolios(portfolios: &Portfolios) -> Result<(), String> {
-    storage::stable_save((portfolios.clone(),))
-        .map_err(|e| format!("Failed to save portfolios: {}", e))
+/// Save portfolios, open orders, and the next order ID to stable storage
+fn save_state(portfolios: &Portfolios, orders: &Orders, next_order_id: u64) -> Result<(), String> {
+    storage::stable_save((portfolios.clone(), orders.clone(), next_order_id))
+        .map_err(|e| format!("Failed to save state: {}", e))
 }
 
 // ===== Public API Methods =====
 
 /// Initialize a user's portfolio with a starting USD balance
-/// 
+///
 /// # Parameters
 /// * `user_id` - User identifier
 /// * `initial_balance` - Starting USD balance (default: 10000.0)
-/// 
+///
 /// # Returns
 /// A confirmation message
 #[update]
 pub async fn initialize_portfolio(user_id: String, initial_balance: Option<f64>) -> String {
     let mut portfolios = load_portfolios();
-    
+
     if portfolios.contains_key(&user_id) {
         return format!("Portfolio for user {} already exists", user_id);
     }
-    
-    let balance = initial_balance.unwrap_or(10000.0);
-    
+
+    let balance = match Decimal::from_f64(initial_balance.unwrap_or(10000.0)) {
+        Some(balance) => balance,
+        None => return "Invalid initial balance".to_string(),
+    };
+
     let portfolio = Portfolio {
-        usd_balance: balance,
+        usd_balance: balance.to_string(),
         holdings: HashMap::new(),
         transactions: Vec::new(),
+        lots: HashMap::new(),
     };
-    
+
     portfolios.insert(user_id.clone(), portfolio);
-    
+
     match save_portfolios(&portfolios) {
-        Ok(_) => format!("Portfolio initialized for {} with ${:.2} USD", user_id, balance),
+        Ok(_) => format!("Portfolio initialized for {} with ${} USD", user_id, balance),
         Err(e) => format!("Failed to initialize portfolio: {}", e),
     }
 }
 
 /// Get a user's portfolio
-/// 
+///
 /// # Parameters
 /// * `user_id` - User identifier
-/// 
+///
 /// # Returns
 /// The user's portfolio or an error message
 #[query]
 pub fn get_portfolio(user_id: String) -> Result<Portfolio, String> {
     let portfolios = load_portfolios();
-    
+
     portfolios.get(&user_id)
         .cloned()
         .ok_or_else(|| format!("Portfolio not found for user {}", user_id))
 }
 
 /// Buy cryptocurrency
-/// 
+///
 /// # Parameters
 /// * `user_id` - User identifier
 /// * `coin_id` - CoinGecko cryptocurrency ID
 /// * `amount_usd` - Amount in USD to spend
-/// 
+///
 /// # Returns
 /// A confirmation message or error
 #[update]
@@ -139,49 +231,43 @@ pub async fn buy_cryptocurrency(user_id: String, coin_id: String, amount_usd: f6
     if amount_usd <= 0.0 {
         return "Amount must be greater than zero".to_string();
     }
-    
+    let amount_usd = match Decimal::from_f64(amount_usd) {
+        Some(amount_usd) => amount_usd,
+        None => return "Invalid USD amount".to_string(),
+    };
+
     let mut portfolios = load_portfolios();
-    
+
     // Check if user exists
     let portfolio = match portfolios.get_mut(&user_id) {
         Some(p) => p,
         None => return format!("Portfolio not found for user {}", user_id),
     };
-    
+
+    let usd_balance = match parse_decimal(&portfolio.usd_balance) {
+        Ok(balance) => balance,
+        Err(e) => return format!("Corrupt portfolio balance: {}", e),
+    };
+
     // Check if user has enough USD
-    if portfolio.usd_balance < amount_usd {
-        return format!("Insufficient USD balance. You have ${:.2}, but need ${:.2}", 
-                      portfolio.usd_balance, amount_usd);
+    if usd_balance < amount_usd {
+        return format!("Insufficient USD balance. You have ${}, but need ${}",
+                      usd_balance, amount_usd);
     }
-    
+
     // Get current price from CoinGecko
     let normalized_id = normalize_coin_id(&coin_id);
-    
+
     match get_crypto_price(&normalized_id).await {
         Ok(price) => {
-            // Calculate amount of crypto to buy
-            let crypto_amount = amount_usd / price;
-            
-            // Update portfolio
-            portfolio.usd_balance -= amount_usd;
-            
-            *portfolio.holdings.entry(normalized_id.clone()).or_insert(0.0) += crypto_amount;
-            
-            // Record transaction
-            let transaction = Transaction {
-                transaction_type: TransactionType::Buy,
-                coin_id: normalized_id.clone(),
-                amount: crypto_amount,
-                price,
-                total_value: amount_usd,
-                timestamp: api::time(),
+            let crypto_amount = match execute_buy(portfolio, &normalized_id, price, amount_usd) {
+                Ok(crypto_amount) => crypto_amount,
+                Err(e) => return e,
             };
-            
-            portfolio.transactions.push(transaction);
-            
+
             // Save updated portfolios
             match save_portfolios(&portfolios) {
-                Ok(_) => format!("Successfully bought {:.6} {} for ${:.2} USD", 
+                Ok(_) => format!("Successfully bought {} {} for ${} USD",
                                 crypto_amount, normalized_id, amount_usd),
                 Err(e) => format!("Transaction recorded but failed to save: {}", e),
             }
@@ -191,12 +277,12 @@ pub async fn buy_cryptocurrency(user_id: String, coin_id: String, amount_usd: f6
 }
 
 /// Sell cryptocurrency
-/// 
+///
 /// # Parameters
 /// * `user_id` - User identifier
 /// * `coin_id` - CoinGecko cryptocurrency ID
 /// * `crypto_amount` - Amount of cryptocurrency to sell
-/// 
+///
 /// # Returns
 /// A confirmation message or error
 #[update]
@@ -204,58 +290,46 @@ pub async fn sell_cryptocurrency(user_id: String, coin_id: String, crypto_amount
     if crypto_amount <= 0.0 {
         return "Amount must be greater than zero".to_string();
     }
-    
+    let crypto_amount = match Decimal::from_f64(crypto_amount) {
+        Some(crypto_amount) => crypto_amount,
+        None => return "Invalid crypto amount".to_string(),
+    };
+
     let mut portfolios = load_portfolios();
-    
+
     // Check if user exists
     let portfolio = match portfolios.get_mut(&user_id) {
         Some(p) => p,
         None => return format!("Portfolio not found for user {}", user_id),
     };
-    
+
     let normalized_id = normalize_coin_id(&coin_id);
-    
+
     // Check if user has enough of the cryptocurrency
-    let user_crypto_amount = portfolio.holdings.get(&normalized_id).cloned().unwrap_or(0.0);
-    
+    let user_crypto_amount = match portfolio.holdings.get(&normalized_id) {
+        Some(existing) => match parse_decimal(existing) {
+            Ok(existing) => existing,
+            Err(e) => return format!("Corrupt holding balance: {}", e),
+        },
+        None => Decimal::ZERO,
+    };
+
     if user_crypto_amount < crypto_amount {
-        return format!("Insufficient {} balance. You have {:.6}, but want to sell {:.6}", 
+        return format!("Insufficient {} balance. You have {}, but want to sell {}",
                       normalized_id, user_crypto_amount, crypto_amount);
     }
-    
+
     // Get current price from CoinGecko
     match get_crypto_price(&normalized_id).await {
         Ok(price) => {
-            // Calculate USD value
-            let usd_value = crypto_amount * price;
-            
-            // Update portfolio
-            portfolio.usd_balance += usd_value;
-            
-            if let Some(holding) = portfolio.holdings.get_mut(&normalized_id) {
-                *holding -= crypto_amount;
-                
-                // Remove the entry if balance is zero or very close to zero
-                if *holding < 0.000001 {
-                    portfolio.holdings.remove(&normalized_id);
-                }
-            }
-            
-            // Record transaction
-            let transaction = Transaction {
-                transaction_type: TransactionType::Sell,
-                coin_id: normalized_id.clone(),
-                amount: crypto_amount,
-                price,
-                total_value: usd_value,
-                timestamp: api::time(),
+            let usd_value = match execute_sell(portfolio, &normalized_id, price, crypto_amount) {
+                Ok(usd_value) => usd_value,
+                Err(e) => return e,
             };
-            
-            portfolio.transactions.push(transaction);
-            
+
             // Save updated portfolios
             match save_portfolios(&portfolios) {
-                Ok(_) => format!("Successfully sold {:.6} {} for ${:.2} USD", 
+                Ok(_) => format!("Successfully sold {} {} for ${} USD",
                                 crypto_amount, normalized_id, usd_value),
                 Err(e) => format!("Transaction recorded but failed to save: {}", e),
             }
@@ -264,38 +338,215 @@ pub async fn sell_cryptocurrency(user_id: String, coin_id: String, crypto_amount
     }
 }
 
+/// Swap one cryptocurrency directly for another without routing through USD
+///
+/// Internally this still prices both coins in USD (so the FIFO cost-basis lots and realized
+/// P&L stay accurate), but the user's USD balance is untouched and both coins are priced
+/// in a single outcall, via the batched price helper.
+///
+/// # Parameters
+/// * `user_id` - User identifier
+/// * `from_coin_id` - CoinGecko ID of the coin to swap away
+/// * `to_coin_id` - CoinGecko ID of the coin to receive
+/// * `from_amount` - Amount of `from_coin_id` to swap
+///
+/// # Returns
+/// A confirmation message or error
+#[update]
+pub async fn swap_cryptocurrency(user_id: String, from_coin_id: String, to_coin_id: String, from_amount: f64) -> String {
+    if from_amount <= 0.0 {
+        return "Amount must be greater than zero".to_string();
+    }
+    let from_amount = match Decimal::from_f64(from_amount) {
+        Some(from_amount) => from_amount,
+        None => return "Invalid crypto amount".to_string(),
+    };
+
+    let from_id = normalize_coin_id(&from_coin_id);
+    let to_id = normalize_coin_id(&to_coin_id);
+    if from_id == to_id {
+        return "Cannot swap a cryptocurrency for itself".to_string();
+    }
+
+    let mut portfolios = load_portfolios();
+
+    let portfolio = match portfolios.get_mut(&user_id) {
+        Some(p) => p,
+        None => return format!("Portfolio not found for user {}", user_id),
+    };
+
+    let held_amount = match portfolio.holdings.get(&from_id) {
+        Some(existing) => match parse_decimal(existing) {
+            Ok(existing) => existing,
+            Err(e) => return format!("Corrupt holding balance: {}", e),
+        },
+        None => Decimal::ZERO,
+    };
+
+    if held_amount < from_amount {
+        return format!("Insufficient {} balance. You have {}, but want to swap {}",
+                      from_id, held_amount, from_amount);
+    }
+
+    let prices = match get_crypto_prices(&[from_id.clone(), to_id.clone()]).await {
+        Ok(prices) => prices,
+        Err(e) => return format!("Failed to get prices: {}", e),
+    };
+    let from_price = match prices.get(&from_id) {
+        Some(price) => *price,
+        None => return format!("Failed to get price for {}", from_id),
+    };
+    let to_price = match prices.get(&to_id) {
+        Some(price) => *price,
+        None => return format!("Failed to get price for {}", to_id),
+    };
+
+    let usd_equivalent = match checked_mul(from_amount, from_price) {
+        Ok(usd_equivalent) => usd_equivalent,
+        Err(e) => return format!("Failed to compute swap value: {}", e),
+    };
+    let to_amount = match checked_div(usd_equivalent, to_price) {
+        Ok(to_amount) => to_amount,
+        Err(e) => return format!("Failed to compute exchange amount: {}", e),
+    };
+
+    // Decrement the `from` holding, consuming its FIFO lots exactly as a sell would
+    let remaining_holding = held_amount - from_amount;
+    if remaining_holding < DUST_THRESHOLD {
+        portfolio.holdings.remove(&from_id);
+    } else {
+        portfolio.holdings.insert(from_id.clone(), remaining_holding.to_string());
+    }
+    let realized_pnl = {
+        let lots = portfolio.lots.entry(from_id.clone()).or_default();
+        match consume_lots_fifo(lots, from_amount, from_price) {
+            Ok(realized_pnl) => realized_pnl,
+            Err(e) => return format!("Failed to compute realized gain/loss: {}", e),
+        }
+    };
+
+    // Increment the `to` holding and open a new cost-basis lot at today's price
+    let to_holding = match portfolio.holdings.get(&to_id) {
+        Some(existing) => match parse_decimal(existing) {
+            Ok(existing) => existing,
+            Err(e) => return format!("Corrupt holding balance: {}", e),
+        },
+        None => Decimal::ZERO,
+    };
+    portfolio.holdings.insert(to_id.clone(), (to_holding + to_amount).to_string());
+    portfolio.lots.entry(to_id.clone()).or_default().push_back(Lot {
+        amount_remaining: to_amount.to_string(),
+        price_paid: to_price.to_string(),
+    });
+
+    // Record the swap as a linked sell/buy pair sharing a timestamp, so it flows through the
+    // same transaction history and realized-P&L accounting as an ordinary sell and buy
+    let timestamp = api::time();
+    portfolio.transactions.push(Transaction {
+        transaction_type: TransactionType::Sell,
+        coin_id: from_id.clone(),
+        amount: from_amount.to_string(),
+        price: from_price.to_string(),
+        total_value: usd_equivalent.to_string(),
+        timestamp,
+        realized_pnl: realized_pnl.to_string(),
+    });
+    portfolio.transactions.push(Transaction {
+        transaction_type: TransactionType::Buy,
+        coin_id: to_id.clone(),
+        amount: to_amount.to_string(),
+        price: to_price.to_string(),
+        total_value: usd_equivalent.to_string(),
+        timestamp,
+        realized_pnl: Decimal::ZERO.to_string(),
+    });
+
+    match save_portfolios(&portfolios) {
+        Ok(_) => format!("Successfully swapped {} {} for {} {}", from_amount, from_id, to_amount, to_id),
+        Err(e) => format!("Transaction recorded but failed to save: {}", e),
+    }
+}
+
 /// Get current portfolio value in USD
-/// 
+///
 /// # Parameters
 /// * `user_id` - User identifier
-/// 
+///
 /// # Returns
-/// Total portfolio value or error
+/// Total portfolio value as a decimal string, or an error
 #[update]
-pub async fn get_portfolio_value(user_id: String) -> Result<f64, String> {
+pub async fn get_portfolio_value(user_id: String) -> Result<String, String> {
     let portfolios = load_portfolios();
-    
+
     let portfolio = match portfolios.get(&user_id) {
         Some(p) => p,
         None => return Err(format!("Portfolio not found for user {}", user_id)),
     };
-    
-    let mut total_value = portfolio.usd_balance;
-    
-    // Calculate value of each cryptocurrency holding
+
+    let mut total_value = parse_decimal(&portfolio.usd_balance)?;
+
+    // Price every held coin in a single batched outcall rather than one outcall per coin
+    let coin_ids: Vec<String> = portfolio.holdings.keys().cloned().collect();
+    let prices = get_crypto_prices(&coin_ids).await?;
+
     for (coin_id, amount) in &portfolio.holdings {
-        match get_crypto_price(coin_id).await {
-            Ok(price) => {
-                total_value += amount * price;
+        match prices.get(coin_id) {
+            Some(price) => {
+                let amount = parse_decimal(amount)?;
+                total_value = checked_add(total_value, checked_mul(amount, *price)?)?;
             },
-            Err(e) => {
-                api::print(format!("Failed to get price for {}: {}", coin_id, e));
-                // Continue with other coins even if one fails
+            None => {
+                api::print(format!("Failed to get price for {}", coin_id));
+                // Continue with other coins even if one is missing from the response
             }
         }
     }
-    
-    Ok(total_value)
+
+    Ok(total_value.to_string())
+}
+
+/// Price a watchlist of coins in a single batched CoinGecko call
+///
+/// # Parameters
+/// * `coin_ids` - CoinGecko cryptocurrency IDs to price
+///
+/// # Returns
+/// A map of coin ID to current USD price, as decimal strings, or an error
+#[update]
+pub async fn get_prices(coin_ids: Vec<String>) -> Result<HashMap<String, String>, String> {
+    let normalized: Vec<String> = coin_ids.iter().map(|id| normalize_coin_id(id)).collect();
+    let prices = get_crypto_prices(&normalized).await?;
+    Ok(prices.into_iter().map(|(id, price)| (id, price.to_string())).collect())
+}
+
+/// Get the total realized gain/loss for a user's holding of a coin, summed across all
+/// FIFO-matched sells recorded in their transaction history
+///
+/// # Parameters
+/// * `user_id` - User identifier
+/// * `coin_id` - CoinGecko cryptocurrency ID
+///
+/// # Returns
+/// The sum of `realized_pnl` across matching sell transactions, as a decimal string, or an error
+#[query]
+pub fn get_realized_pnl(user_id: String, coin_id: String) -> Result<String, String> {
+    let portfolios = load_portfolios();
+
+    let portfolio = portfolios
+        .get(&user_id)
+        .ok_or_else(|| format!("Portfolio not found for user {}", user_id))?;
+
+    let normalized_id = normalize_coin_id(&coin_id);
+
+    let mut total = Decimal::ZERO;
+    for t in portfolio
+        .transactions
+        .iter()
+        .filter(|t| t.transaction_type == TransactionType::Sell && t.coin_id == normalized_id)
+    {
+        total = checked_add(total, parse_decimal(&t.realized_pnl)?)?;
+    }
+    Ok(total.to_string())
 }
 
 /// Get list of supported cryptocurrencies
@@ -315,8 +566,493 @@ pub fn get_supported_cryptocurrencies() -> Vec<String> {
     ]
 }
 
+// ===== Limit Orders =====
+
+/// Place a limit/trigger order that fills later, once the market price crosses `target_price`,
+/// instead of trading at the current market price
+///
+/// # Parameters
+/// * `user_id` - User identifier; must already have a portfolio
+/// * `coin_id` - CoinGecko cryptocurrency ID
+/// * `side` - Whether the order buys or sells `coin_id` once it fills
+/// * `target_price` - Market price that triggers the order
+/// * `trigger` - Whether the order fills when price rises to/above or falls to/below `target_price`
+/// * `amount` - For a buy, USD amount to spend; for a sell, amount of `coin_id` to sell
+///
+/// # Returns
+/// The new order's ID, or an error
+#[update]
+pub fn place_limit_order(
+    user_id: String,
+    coin_id: String,
+    side: TransactionType,
+    target_price: f64,
+    trigger: OrderTrigger,
+    amount: f64,
+) -> Result<String, String> {
+    if target_price <= 0.0 {
+        return Err("Target price must be greater than zero".to_string());
+    }
+    if amount <= 0.0 {
+        return Err("Amount must be greater than zero".to_string());
+    }
+    let target_price = Decimal::from_f64(target_price).ok_or_else(|| "Invalid target price".to_string())?;
+    let amount = Decimal::from_f64(amount).ok_or_else(|| "Invalid amount".to_string())?;
+
+    let portfolios = load_portfolios();
+    if !portfolios.contains_key(&user_id) {
+        return Err(format!("Portfolio not found for user {}", user_id));
+    }
+
+    let mut orders = load_orders();
+    let next_order_id = load_next_order_id();
+    let id = next_order_id.to_string();
+
+    orders.insert(id.clone(), LimitOrder {
+        id: id.clone(),
+        user_id,
+        coin_id: normalize_coin_id(&coin_id),
+        side,
+        target_price: target_price.to_string(),
+        trigger,
+        amount: amount.to_string(),
+    });
+
+    save_orders(&orders, next_order_id + 1)?;
+    Ok(id)
+}
+
+/// Cancel an open limit order
+///
+/// # Parameters
+/// * `id` - Order ID returned by [`place_limit_order`]
+///
+/// # Returns
+/// A confirmation message or an error if no such order is open
+#[update]
+pub fn cancel_limit_order(id: String) -> Result<String, String> {
+    let mut orders = load_orders();
+    if orders.remove(&id).is_none() {
+        return Err(format!("No open order with id {}", id));
+    }
+    let next_order_id = load_next_order_id();
+    save_orders(&orders, next_order_id)?;
+    Ok(format!("Order {} cancelled", id))
+}
+
+/// List a user's open limit orders
+#[query]
+pub fn list_open_orders(user_id: String) -> Vec<LimitOrder> {
+    load_orders()
+        .into_values()
+        .filter(|order| order.user_id == user_id)
+        .collect()
+}
+
+/// Check every open order against current market prices and fill the ones whose trigger has
+/// crossed, callable periodically by a heartbeat or external timer
+///
+/// Batch-fetches prices for all distinct coins with open orders in a single outcall. An order
+/// that crosses its trigger but whose portfolio lacks sufficient balance is left open so it can
+/// fill on a later tick rather than being cancelled.
+///
+/// # Returns
+/// One status line per order that was evaluated this tick (filled, left open, or errored);
+/// orders whose trigger hasn't crossed yet are omitted
+#[update]
+pub async fn process_orders() -> Vec<String> {
+    let mut orders = load_orders();
+    if orders.is_empty() {
+        return Vec::new();
+    }
+
+    let coin_ids: Vec<String> = {
+        let mut ids: Vec<String> = orders.values().map(|order| order.coin_id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+
+    let prices = match get_crypto_prices(&coin_ids).await {
+        Ok(prices) => prices,
+        Err(e) => return vec![format!("Failed to fetch prices for open orders: {}", e)],
+    };
+
+    let mut portfolios = load_portfolios();
+    let mut results = Vec::new();
+    let mut filled_ids = Vec::new();
+
+    for order in orders.values() {
+        let price = match prices.get(&order.coin_id) {
+            Some(price) => *price,
+            None => continue, // Coin missing from the batch response; try again next tick
+        };
+        let target_price = match parse_decimal(&order.target_price) {
+            Ok(target_price) => target_price,
+            Err(e) => {
+                results.push(format!("Order {}: {}", order.id, e));
+                continue;
+            }
+        };
+
+        let crossed = match order.trigger {
+            OrderTrigger::Above => price >= target_price,
+            OrderTrigger::Below => price <= target_price,
+        };
+        if !crossed {
+            continue;
+        }
+
+        let amount = match parse_decimal(&order.amount) {
+            Ok(amount) => amount,
+            Err(e) => {
+                results.push(format!("Order {}: {}", order.id, e));
+                continue;
+            }
+        };
+
+        let portfolio = match portfolios.get_mut(&order.user_id) {
+            Some(portfolio) => portfolio,
+            None => {
+                results.push(format!("Order {}: portfolio not found for {}", order.id, order.user_id));
+                continue;
+            }
+        };
+
+        let outcome = match order.side {
+            TransactionType::Buy => execute_buy(portfolio, &order.coin_id, price, amount),
+            TransactionType::Sell => execute_sell(portfolio, &order.coin_id, price, amount),
+        };
+
+        match outcome {
+            Ok(_) => {
+                results.push(format!("Order {} filled: {:?} {} {} @ ${}", order.id, order.side, order.amount, order.coin_id, price));
+                filled_ids.push(order.id.clone());
+            }
+            // Most likely insufficient balance; leave the order open to retry on a later tick
+            Err(e) => results.push(format!("Order {} not filled: {}", order.id, e)),
+        }
+    }
+
+    if !filled_ids.is_empty() {
+        if let Err(e) = save_portfolios(&portfolios) {
+            results.push(format!("Failed to save portfolios: {}", e));
+        }
+        for id in &filled_ids {
+            orders.remove(id);
+        }
+    }
+
+    let next_order_id = load_next_order_id();
+    if let Err(e) = save_orders(&orders, next_order_id) {
+        results.push(format!("Failed to save orders: {}", e));
+    }
+
+    results
+}
+
+/// Execute a market buy of `coin_id` against an already-loaded portfolio, updating balance,
+/// holdings, FIFO lots, and transaction history exactly as [`buy_cryptocurrency`] does
+///
+/// Shared with `alerts::apply_alert_action` so an alert-triggered buy books identically to a
+/// manually-placed one instead of maintaining a second copy of this accounting.
+pub(crate) fn execute_buy(portfolio: &mut Portfolio, coin_id: &str, price: Decimal, amount_usd: Decimal) -> Result<Decimal, String> {
+    let usd_balance = parse_decimal(&portfolio.usd_balance)?;
+    if usd_balance < amount_usd {
+        return Err(format!("Insufficient USD balance. You have ${}, but need ${}", usd_balance, amount_usd));
+    }
+
+    let crypto_amount = checked_div(amount_usd, price)?;
+    portfolio.usd_balance = checked_sub(usd_balance, amount_usd)?.to_string();
+
+    let holding = match portfolio.holdings.get(coin_id) {
+        Some(existing) => parse_decimal(existing)?,
+        None => Decimal::ZERO,
+    };
+    portfolio.holdings.insert(coin_id.to_string(), checked_add(holding, crypto_amount)?.to_string());
+
+    portfolio.lots.entry(coin_id.to_string()).or_default().push_back(Lot {
+        amount_remaining: crypto_amount.to_string(),
+        price_paid: price.to_string(),
+    });
+
+    portfolio.transactions.push(Transaction {
+        transaction_type: TransactionType::Buy,
+        coin_id: coin_id.to_string(),
+        amount: crypto_amount.to_string(),
+        price: price.to_string(),
+        total_value: amount_usd.to_string(),
+        timestamp: api::time(),
+        realized_pnl: Decimal::ZERO.to_string(),
+    });
+
+    Ok(crypto_amount)
+}
+
+/// Execute a market sell of `coin_id` against an already-loaded portfolio, updating balance,
+/// holdings, FIFO lots, and transaction history exactly as [`sell_cryptocurrency`] does
+///
+/// Shared with `alerts::apply_alert_action` so an alert-triggered sell books identically to a
+/// manually-placed one instead of maintaining a second copy of this accounting.
+pub(crate) fn execute_sell(portfolio: &mut Portfolio, coin_id: &str, price: Decimal, crypto_amount: Decimal) -> Result<Decimal, String> {
+    let held = match portfolio.holdings.get(coin_id) {
+        Some(existing) => parse_decimal(existing)?,
+        None => Decimal::ZERO,
+    };
+    if held < crypto_amount {
+        return Err(format!("Insufficient {} balance. You have {}, but want to sell {}", coin_id, held, crypto_amount));
+    }
+
+    let usd_value = checked_mul(crypto_amount, price)?;
+    let usd_balance = parse_decimal(&portfolio.usd_balance)?;
+    portfolio.usd_balance = checked_add(usd_balance, usd_value)?.to_string();
+
+    let remaining = checked_sub(held, crypto_amount)?;
+    if remaining < DUST_THRESHOLD {
+        portfolio.holdings.remove(coin_id);
+    } else {
+        portfolio.holdings.insert(coin_id.to_string(), remaining.to_string());
+    }
+
+    let realized_pnl = {
+        let lots = portfolio.lots.entry(coin_id.to_string()).or_default();
+        consume_lots_fifo(lots, crypto_amount, price)?
+    };
+
+    portfolio.transactions.push(Transaction {
+        transaction_type: TransactionType::Sell,
+        coin_id: coin_id.to_string(),
+        amount: crypto_amount.to_string(),
+        price: price.to_string(),
+        total_value: usd_value.to_string(),
+        timestamp: api::time(),
+        realized_pnl: realized_pnl.to_string(),
+    });
+
+    Ok(usd_value)
+}
+
+// ===== CSV Import/Export =====
+
+/// Import transaction history from CSV rows of `type,coin_id,amount,price,timestamp`
+///
+/// Rows are replayed in timestamp order against a freshly reset portfolio state: buys debit
+/// USD and credit crypto holdings, sells do the reverse, each opening or consuming FIFO
+/// cost-basis lots exactly as a live trade would. A header row matching the column names is
+/// skipped if present. The whole import is rejected, with a 1-indexed line-numbered error, if
+/// any row is malformed, rather than partially applying it.
+///
+/// # Parameters
+/// * `user_id` - User identifier; must already have a portfolio
+/// * `csv` - CSV text with one `type,coin_id,amount,price,timestamp` row per transaction
+///
+/// # Returns
+/// A confirmation message, or a line-numbered error describing the first malformed row
+#[update]
+pub fn import_transactions_csv(user_id: String, csv: String) -> String {
+    let mut portfolios = load_portfolios();
+    if !portfolios.contains_key(&user_id) {
+        return format!("Portfolio not found for user {}", user_id);
+    }
+
+    let mut rows = Vec::new();
+    for (i, line) in csv.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line_no == 1 && line.eq_ignore_ascii_case("type,coin_id,amount,price,timestamp") {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 5 {
+            return format!("Line {}: expected 5 columns, found {}", line_no, fields.len());
+        }
+
+        let transaction_type = match fields[0].trim().to_lowercase().as_str() {
+            "buy" => TransactionType::Buy,
+            "sell" => TransactionType::Sell,
+            other => return format!("Line {}: unknown transaction type '{}'", line_no, other),
+        };
+        let coin_id = normalize_coin_id(fields[1].trim());
+        let amount = match parse_decimal(fields[2].trim()) {
+            Ok(amount) => amount,
+            Err(e) => return format!("Line {}: invalid amount: {}", line_no, e),
+        };
+        let price = match parse_decimal(fields[3].trim()) {
+            Ok(price) => price,
+            Err(e) => return format!("Line {}: invalid price: {}", line_no, e),
+        };
+        let timestamp: u64 = match fields[4].trim().parse() {
+            Ok(timestamp) => timestamp,
+            Err(e) => return format!("Line {}: invalid timestamp: {}", line_no, e),
+        };
+
+        rows.push((line_no, transaction_type, coin_id, amount, price, timestamp));
+    }
+
+    rows.sort_by_key(|(_, _, _, _, _, timestamp)| *timestamp);
+    let imported = rows.len();
+
+    let portfolio = portfolios.get_mut(&user_id).expect("checked above");
+    portfolio.usd_balance = Decimal::ZERO.to_string();
+    portfolio.holdings = HashMap::new();
+    portfolio.transactions = Vec::new();
+    portfolio.lots = HashMap::new();
+
+    for (line_no, transaction_type, coin_id, amount, price, timestamp) in rows {
+        let usd_balance = parse_decimal(&portfolio.usd_balance).expect("always written as a valid Decimal");
+        let total_value = match checked_mul(amount, price) {
+            Ok(total_value) => total_value,
+            Err(e) => return format!("Failed to replay imported transactions: {}", e),
+        };
+        let holding = match portfolio.holdings.get(&coin_id) {
+            Some(existing) => match parse_decimal(existing) {
+                Ok(existing) => existing,
+                Err(e) => return format!("Failed to replay imported transactions: {}", e),
+            },
+            None => Decimal::ZERO,
+        };
+
+        let realized_pnl = match transaction_type {
+            TransactionType::Buy => {
+                portfolio.usd_balance = (usd_balance - total_value).to_string();
+                portfolio.holdings.insert(coin_id.clone(), (holding + amount).to_string());
+                portfolio.lots.entry(coin_id.clone()).or_default().push_back(Lot {
+                    amount_remaining: amount.to_string(),
+                    price_paid: price.to_string(),
+                });
+                Decimal::ZERO
+            }
+            TransactionType::Sell => {
+                if holding < amount {
+                    return format!(
+                        "Line {}: insufficient {} balance at this point in the replay. Have {}, but row sells {}",
+                        line_no, coin_id, holding, amount
+                    );
+                }
+                portfolio.usd_balance = (usd_balance + total_value).to_string();
+                let remaining = holding - amount;
+                if remaining < DUST_THRESHOLD {
+                    portfolio.holdings.remove(&coin_id);
+                } else {
+                    portfolio.holdings.insert(coin_id.clone(), remaining.to_string());
+                }
+                let lots = portfolio.lots.entry(coin_id.clone()).or_default();
+                match consume_lots_fifo(lots, amount, price) {
+                    Ok(realized_pnl) => realized_pnl,
+                    Err(e) => return format!("Failed to replay imported transactions: {}", e),
+                }
+            }
+        };
+
+        portfolio.transactions.push(Transaction {
+            transaction_type,
+            coin_id,
+            amount: amount.to_string(),
+            price: price.to_string(),
+            total_value: total_value.to_string(),
+            timestamp,
+            realized_pnl: realized_pnl.to_string(),
+        });
+    }
+
+    match save_portfolios(&portfolios) {
+        Ok(_) => format!("Imported {} transactions for {}", imported, user_id),
+        Err(e) => format!("Transactions replayed but failed to save: {}", e),
+    }
+}
+
+/// Export a user's transaction history as CSV rows of `type,coin_id,amount,price,timestamp`,
+/// the same format accepted by [`import_transactions_csv`]
+///
+/// # Parameters
+/// * `user_id` - User identifier
+///
+/// # Returns
+/// CSV text with a header row, or an error if the user has no portfolio
+#[query]
+pub fn export_transactions_csv(user_id: String) -> Result<String, String> {
+    let portfolios = load_portfolios();
+    let portfolio = portfolios
+        .get(&user_id)
+        .ok_or_else(|| format!("Portfolio not found for user {}", user_id))?;
+
+    let mut csv = String::from("type,coin_id,amount,price,timestamp\n");
+    for t in &portfolio.transactions {
+        let transaction_type = match t.transaction_type {
+            TransactionType::Buy => "buy",
+            TransactionType::Sell => "sell",
+        };
+        csv.push_str(&format!("{},{},{},{},{}\n", transaction_type, t.coin_id, t.amount, t.price, t.timestamp));
+    }
+
+    Ok(csv)
+}
+
 // ===== Helper Functions =====
 
+/// Parse a decimal string (USD amount, crypto quantity, or price) stored on a [`Portfolio`],
+/// [`Transaction`], or [`Lot`]
+pub(crate) fn parse_decimal(s: &str) -> Result<Decimal, String> {
+    Decimal::from_str(s).map_err(|e| format!("Invalid decimal value '{}': {}", s, e))
+}
+
+/// Add two decimals, surfacing overflow instead of panicking
+pub(crate) fn checked_add(a: Decimal, b: Decimal) -> Result<Decimal, String> {
+    a.checked_add(b).ok_or_else(|| "Addition overflow".to_string())
+}
+
+/// Multiply two decimals, surfacing overflow instead of panicking
+pub(crate) fn checked_mul(a: Decimal, b: Decimal) -> Result<Decimal, String> {
+    a.checked_mul(b).ok_or_else(|| "Multiplication overflow".to_string())
+}
+
+/// Divide two decimals, surfacing division overflow/by-zero instead of panicking or yielding NaN
+pub(crate) fn checked_div(a: Decimal, b: Decimal) -> Result<Decimal, String> {
+    a.checked_div(b).ok_or_else(|| "Division overflow".to_string())
+}
+
+/// Consume `sell_amount` units from the front of a FIFO lot queue at `sell_price`, returning
+/// the realized gain/loss versus each consumed lot's cost basis
+///
+/// A lot left with a dust remainder (below [`DUST_THRESHOLD`]) is popped entirely rather than
+/// kept open indefinitely.
+pub(crate) fn consume_lots_fifo(lots: &mut VecDeque<Lot>, sell_amount: Decimal, sell_price: Decimal) -> Result<Decimal, String> {
+    let mut remaining = sell_amount;
+    let mut realized_pnl = Decimal::ZERO;
+
+    while remaining > Decimal::ZERO {
+        let lot_amount = match lots.front() {
+            Some(lot) => parse_decimal(&lot.amount_remaining)?,
+            None => break, // Sells should already be capped by the balance check
+        };
+        let lot_price = parse_decimal(&lots.front().expect("checked above").price_paid)?;
+
+        let consumed = remaining.min(lot_amount);
+        let gain = checked_mul(consumed, checked_sub(sell_price, lot_price)?)?;
+        realized_pnl = checked_add(realized_pnl, gain)?;
+
+        let lot_amount_left = checked_sub(lot_amount, consumed)?;
+        remaining = checked_sub(remaining, consumed)?;
+
+        if lot_amount_left < DUST_THRESHOLD {
+            lots.pop_front();
+        } else {
+            lots.front_mut().expect("checked above").amount_remaining = lot_amount_left.to_string();
+        }
+    }
+
+    Ok(realized_pnl)
+}
+
+/// Subtract two decimals, surfacing overflow instead of panicking
+pub(crate) fn checked_sub(a: Decimal, b: Decimal) -> Result<Decimal, String> {
+    a.checked_sub(b).ok_or_else(|| "Subtraction overflow".to_string())
+}
+
 /// Normalize coin ID to match CoinGecko format
 fn normalize_coin_id(coin_id: &str) -> String {
     match coin_id.to_lowercase().as_str() {
@@ -334,8 +1070,87 @@ fn normalize_coin_id(coin_id: &str) -> String {
     }.to_string()
 }
 
-/// Fetch the current price of a cryptocurrency from CoinGecko
-async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
+/// Fetch current prices for many cryptocurrencies in a single CoinGecko outcall
+///
+/// Hits the batched `/simple/price` endpoint instead of issuing one `/coins/{id}` outcall per
+/// coin, so pricing a portfolio or watchlist costs one outcall regardless of holdings count.
+///
+/// # Parameters
+/// * `ids` - CoinGecko cryptocurrency IDs to price; already-normalized
+///
+/// # Returns
+/// A map of coin ID to current USD price, or an error if the outcall itself fails. A coin
+/// absent from CoinGecko's response (e.g. an unrecognized ID) is simply absent from the map
+/// rather than failing the whole batch.
+async fn get_crypto_prices(ids: &[String]) -> Result<HashMap<String, Decimal>, String> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let host = "api.coingecko.com";
+    let url = format!(
+        "https://{}/api/v3/simple/price?ids={}&vs_currencies=usd",
+        host,
+        ids.join(",")
+    );
+
+    let req_headers = vec![
+        HttpHeader {
+            name: "Accept".to_string(),
+            value: "application/json".to_string(),
+        },
+        HttpHeader {
+            name: "User-Agent".to_string(),
+            value: "IC-Canister".to_string(),
+        },
+    ];
+
+    let req = CanisterHttpRequestArgument {
+        url,
+        method: HttpMethod::GET,
+        body: None,
+        max_response_bytes: Some(2_000_000), // 2MB max response
+        transform: Some(TransformContext::from_name("transform".to_string(), vec![])),
+        headers: req_headers,
+    };
+
+    let cycles: u128 = 1_603_146_400 + 10_000; // Add buffer to ensure enough cycles
+
+    match http_request(req, cycles).await {
+        Ok((response,)) => {
+            if response.status != candid::Nat::from(200u64) {
+                let error_body = String::from_utf8(response.body.clone())
+                    .unwrap_or_else(|_| format!("Non-UTF8 error response: {:?}", response.body));
+                return Err(format!("API error (status {}): {}", response.status, error_body));
+            }
+
+            let str_body = String::from_utf8(response.body)
+                .map_err(|e| format!("Invalid UTF-8 in response: {}", e))?;
+
+            let json: Value = serde_json::from_str(&str_body)
+                .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+            let obj = json.as_object()
+                .ok_or_else(|| "Unexpected response shape from /simple/price".to_string())?;
+
+            let mut prices = HashMap::new();
+            for (coin_id, entry) in obj {
+                if let Some(usd) = entry.get("usd") {
+                    let price = Decimal::from_str(&usd.to_string())
+                        .map_err(|e| format!("Invalid price for {}: {}", coin_id, e))?;
+                    prices.insert(coin_id.clone(), price);
+                }
+            }
+
+            Ok(prices)
+        }
+        Err((r, m)) => Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m)),
+    }
+}
+
+/// Fetch the current price of a cryptocurrency from CoinGecko, parsed as a `Decimal` rather
+/// than `f64` so it composes with the rest of this module's checked decimal arithmetic
+async fn get_crypto_price(coin_id: &str) -> Result<Decimal, String> {
     let host = "api.coingecko.com";
     let url = format!(
         "https://{}/api/v3/coins/{}?localization=false&tickers=false&market_data=true&community_data=false&developer_data=false&sparkline=false",
@@ -352,7 +1167,7 @@ async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
             value: "IC-Canister".to_string(),
         },
     ];
-            
+
     let req = CanisterHttpRequestArgument {
         url,
         method: HttpMethod::GET,
@@ -371,17 +1186,69 @@ async fn get_crypto_price(coin_id: &str) -> Result<f64, String> {
                     .unwrap_or_else(|_| format!("Non-UTF8 error response: {:?}", response.body));
                 return Err(format!("API error (status {}): {}", response.status, error_body));
             }
-            
+
             let str_body = String::from_utf8(response.body)
                 .map_err(|e| format!("Invalid UTF-8 in response: {}", e))?;
-            
+
             let json: Value = serde_json::from_str(&str_body)
                 .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-            
-            json["market_data"]["current_price"]["usd"]
-                .as_f64()
-                .ok_or_else(|| "Price data not found in response".to_string())
+
+            let price_str = json["market_data"]["current_price"]["usd"].to_string();
+            Decimal::from_str(&price_str)
+                .map_err(|e| format!("Price data not found in response: {}", e))
         }
         Err((r, m)) => Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lot(amount_remaining: &str, price_paid: &str) -> Lot {
+        Lot {
+            amount_remaining: amount_remaining.to_string(),
+            price_paid: price_paid.to_string(),
+        }
+    }
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn consume_lots_fifo_partially_consumes_the_front_lot() {
+        let mut lots = VecDeque::from(vec![lot("2", "100"), lot("1", "150")]);
+
+        let realized_pnl = consume_lots_fifo(&mut lots, d("1"), d("120")).unwrap();
+
+        assert_eq!(realized_pnl, d("20"));
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots.front().unwrap().amount_remaining, "1");
+    }
+
+    #[test]
+    fn consume_lots_fifo_pops_a_lot_left_below_dust_threshold() {
+        // Selling all but a dust-sized remainder of the front lot should pop it entirely
+        // rather than leaving a sub-threshold amount open forever.
+        let mut lots = VecDeque::from(vec![lot("1", "100"), lot("5", "90")]);
+
+        let realized_pnl = consume_lots_fifo(&mut lots, d("0.9999995"), d("110")).unwrap();
+
+        assert_eq!(realized_pnl, d("9.999995"));
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots.front().unwrap().price_paid, "90");
+    }
+
+    #[test]
+    fn consume_lots_fifo_spans_multiple_lots() {
+        let mut lots = VecDeque::from(vec![lot("2", "100"), lot("3", "120")]);
+
+        let realized_pnl = consume_lots_fifo(&mut lots, d("4"), d("130")).unwrap();
+
+        // 2 @ 100 -> gain 60, 2 @ 120 -> gain 20
+        assert_eq!(realized_pnl, d("80"));
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots.front().unwrap().amount_remaining, "1");
+    }
+}